@@ -0,0 +1,25 @@
+/**
+ * FFMPEG ESCAPING
+ *
+ * Filtergraphs have two quoting levels (see "Notes on filtergraph escaping" in the ffmpeg docs):
+ * 1. Option value: '\', ''' and ':' must be escaped, otherwise "C:/x.ass" is split at the colon.
+ * 2. Filtergraph description: '\', ''', '[', ']', ',' and ';' must be escaped again.
+ *
+ * We pass arguments straight to spawn (no shell), so no third (shell) level is needed.
+ */
+
+/**
+ * Escapes a value to be used as a filter option, e.g. `ass=${escapeFilterValue(path)}`.
+ */
+export const escapeFilterValue = (value: string): string => {
+    const optionLevel = value.replace(/[\\':]/g, (c) => `\\${c}`);
+    return optionLevel.replace(/[\\'\[\],;]/g, (c) => `\\${c}`);
+};
+
+/**
+ * Escapes a path for a `file '...'` line of the concat demuxer list.
+ * Inside single quotes the only special character is the quote itself: ' -> '\''
+ */
+export const escapeConcatPath = (filePath: string): string => {
+    return `'${filePath.replace(/'/g, `'\\''`)}'`;
+};
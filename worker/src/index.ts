@@ -1,6 +1,6 @@
 import 'dotenv/config';
 import { createClient, RealtimeChannel } from '@supabase/supabase-js';
-import { processRenderJob } from './processors/ffmpeg';
+import { processRenderJob, abortActiveRender, killActiveRenderSync } from './processors/ffmpeg';
 
 // ============================================
 // DARK FACTORY - Local Rendering Worker
@@ -17,12 +17,29 @@ if (!SUPABASE_URL || !SUPABASE_SERVICE_KEY) {
 const supabase = createClient(SUPABASE_URL, SUPABASE_SERVICE_KEY);
 
 let channel: RealtimeChannel;
+let currentJobId: string | null = null;
+// true depois que a URL do vídeo final do job atual foi gravada no banco
+let currentJobResultSaved = false;
+let shuttingDown = false;
+
+// Avança o job para o próximo passo do pipeline. Só sai de RENDERING, para não
+// sobrescrever um status que outro processo já mudou.
+async function markRendered(jobId: string) {
+    await supabase.from('jobs').update({
+        status: 'THUMBNAIL_GEN',
+        current_step: 'Gerando Thumbnail (IA)',
+        progress: 80
+    }).eq('id', jobId).eq('status', 'RENDERING');
+}
 
 async function handleJobUpdate(payload: any) {
     const job = payload.new;
 
     // Só processar jobs que estão esperando renderização
     if (job.status !== 'RENDERING_PENDING') return;
+    if (shuttingDown || currentJobId) return;
+    currentJobId = job.id;
+    currentJobResultSaved = false;
 
     console.log(`\n🎬 ════════════════════════════════════════`);
     console.log(`   Novo Job Detectado: ${job.id}`);
@@ -37,18 +54,17 @@ async function handleJobUpdate(payload: any) {
         }).eq('id', job.id);
 
         // Executar a renderização
-        await processRenderJob(supabase, job);
+        await processRenderJob(supabase, job, () => { currentJobResultSaved = true; });
 
         // Atualizar para próximo passo
-        await supabase.from('jobs').update({
-            status: 'THUMBNAIL_GEN',
-            current_step: 'Gerando Thumbnail (IA)',
-            progress: 80
-        }).eq('id', job.id);
+        await markRendered(job.id);
 
         console.log(`✅ Job ${job.id} renderizado com sucesso!`);
 
     } catch (error) {
+        // Interrompido pelo shutdown: o status do job é resolvido em shutdown()
+        if (shuttingDown) return;
+
         console.error(`❌ Erro ao processar job ${job.id}:`, error);
 
         await supabase.from('jobs').update({
@@ -59,6 +75,8 @@ async function handleJobUpdate(payload: any) {
                 message: `Falha na renderização: ${error}`
             }]
         }).eq('id', job.id);
+    } finally {
+        currentJobId = null;
     }
 }
 
@@ -122,11 +140,37 @@ async function main() {
     console.log('\n⏳ Aguardando jobs de renderização...\n');
 }
 
-// Graceful shutdown
-process.on('SIGINT', async () => {
-    console.log('\n🛑 Encerrando worker...');
+// Graceful shutdown (SIGINT no terminal, SIGTERM no `docker stop`)
+async function shutdown(signal: NodeJS.Signals) {
+    if (shuttingDown) return;
+    shuttingDown = true;
+    console.log(`\n🛑 Encerrando worker (${signal})...`);
+
+    const interruptedJobId = currentJobId;
+    if (interruptedJobId) {
+        console.log(`   Abortando renderização do job ${interruptedJobId}...`);
+        await abortActiveRender();
+
+        // Em qualquer fase antes de gravar o vídeo final (download, FFmpeg, verificação,
+        // upload) o job volta para a fila; depois disso ele só precisa avançar.
+        // Nos dois casos só se ainda estiver em RENDERING, para não desfazer um update
+        // que já chegou ao banco.
+        if (currentJobResultSaved) {
+            await markRendered(interruptedJobId);
+        } else {
+            await supabase.from('jobs').update({
+                status: 'RENDERING_PENDING',
+                current_step: 'Renderização interrompida (worker encerrado)'
+            }).eq('id', interruptedJobId).eq('status', 'RENDERING');
+        }
+    }
+
     if (channel) await supabase.removeChannel(channel);
     process.exit(0);
-});
+}
+
+process.on('SIGINT', shutdown);
+process.on('SIGTERM', shutdown);
+process.on('exit', killActiveRenderSync);
 
 main();
@@ -7,12 +7,13 @@ import { generateVideoScriptAndPrompts, generateSpeech, generateImage, generateV
 import { smartChunkScript, ScriptChunk } from './smartChunker';
 import { generateAssContent } from './subtitleGenerator';
 import { alignStoryboardToAudio } from './alignmentEngine';
-import { pcmToWavBuffer, getAudioDuration, saveBase64ToFile } from './utils';
-import ffmpeg from 'fluent-ffmpeg';
+import { pcmToWavBuffer, getAudioDuration, saveBase64ToFile, spawnHidden } from './utils';
+import { escapeFilterValue } from './ffmpegEscape';
 
 const SUPABASE_URL = process.env.NEXT_PUBLIC_SUPABASE_URL || '';
 const SUPABASE_KEY = process.env.NEXT_PUBLIC_SUPABASE_ANON_KEY || '';
 const supabase = createClient(SUPABASE_URL, SUPABASE_KEY);
+const FFMPEG_PATH = process.env.FFMPEG_PATH || 'ffmpeg';
 
 // Configuração Engine Mockada (deve vir do DB ou env)
 const ENGINE_CONFIG: EngineConfig = {
@@ -34,9 +35,8 @@ export async function processVideoJob(job: VideoJob) {
     const workDir = path.resolve(__dirname, `../temp/${jobId}`);
 
     // Cria diretórios de trabalho
-    if (!fs.existsSync(workDir)) fs.mkdirSync(workDir, { recursive: true });
-    if (!fs.existsSync(path.join(workDir, 'images'))) fs.mkdirSync(path.join(workDir, 'images'));
-    if (!fs.existsSync(path.join(workDir, 'audio'))) fs.mkdirSync(path.join(workDir, 'audio'));
+    await fs.promises.mkdir(path.join(workDir, 'images'), { recursive: true });
+    await fs.promises.mkdir(path.join(workDir, 'audio'), { recursive: true });
 
     await log(jobId, `Iniciando processamento no diretório: ${workDir}`, 'INFO');
 
@@ -175,39 +175,48 @@ export async function processVideoJob(job: VideoJob) {
 }
 
 async function renderVideoFFmpeg(workDir: string, storyboard: StoryboardSegment[], audioPath: string, assPath: string, outputPath: string, format: string) {
-    return new Promise<void>((resolve, reject) => {
-        const command = ffmpeg();
+    // Filtros complexos (Zoompan + Subtitles)
+    const resolution = format === 'SHORTS' ? "1080:1920" : "1920:1080";
+
+    // Simplificado para teste: Apenas resize e legendas
+    // Zoompan é complexo de acertar sem ajuste fino
+    const filterComplex = [
+        `[0:v]scale=${resolution}:force_original_aspect_ratio=increase,crop=${resolution.replace(':', '/')}[v_scaled]`,
+        `[v_scaled]ass=${escapeFilterValue(assPath.replace(/\\/g, '/'))}[v_subbed]` // Forward slashes + escape do ':' do drive no Windows
+    ].join(';');
+
+    const args = [
+        // Input de Imagens (Pattern) - ffmpeg espera %03d.png
+        '-loop', '1',
+        '-t', `${storyboard.reduce((acc, s) => acc + s.duration, 0) + 5}`, // Duração total + margem
+        '-i', path.join(workDir, 'images', '%03d.png'),
+        // Input de Áudio
+        '-i', audioPath,
+        '-filter_complex', filterComplex,
+        '-map', '[v_subbed]',
+        '-map', '1:a',
+        '-c:v', 'libx264',
+        '-preset', 'ultrafast', // Para dev
+        '-shortest',
+        '-pix_fmt', 'yuv420p',
+        '-movflags', '+faststart',
+        '-y',
+        outputPath
+    ];
 
-        // Input de Imagens (Pattern)
-        // ffmpeg espera %03d.png
-        command.input(path.join(workDir, 'images', '%03d.png'))
-            .inputOptions(['-loop 1', `-t ${storyboard.reduce((acc, s) => acc + s.duration, 0) + 5}`]); // Duração total + margem
+    return new Promise<void>((resolve, reject) => {
+        const command = spawnHidden(FFMPEG_PATH, args);
+        let stderrTail = '';
 
-        // Input de Áudio
-        command.input(audioPath);
-
-        // Filtros complexos (Zoompan + Subtitles)
-        const resolution = format === 'SHORTS' ? "1080:1920" : "1920:1080";
-
-        // Simplificado para teste: Apenas resize e legendas
-        // Zoompan é complexo de acertar sem ajuste fino
-        command.complexFilter([
-            `[0:v]scale=${resolution}:force_original_aspect_ratio=increase,crop=${resolution.replace(':', '/')}[v_scaled]`,
-            `[v_scaled]ass=${assPath.replace(/\\/g, '/')}[v_subbed]` // Caminho do ASS deve ter forward slashes
-        ]);
-
-        command.outputOptions([
-            '-map [v_subbed]',
-            '-map 1:a',
-            '-c:v libx264',
-            '-preset ultrafast', // Para dev
-            '-shortest',
-            '-pix_fmt yuv420p'
-        ]);
-
-        command.save(outputPath)
-            .on('end', () => resolve())
-            .on('error', (err) => reject(err));
+        command.stderr.on('data', (data) => {
+            stderrTail = (stderrTail + data.toString()).slice(-4000);
+        });
+        command.on('close', (code, signal) => {
+            if (code === 0) return resolve();
+            const reason = signal ? `encerrado pelo sinal ${signal}` : `saiu com código ${code}`;
+            reject(new Error(`FFmpeg ${reason}\n${stderrTail.trim()}`));
+        });
+        command.on('error', (err) => reject(err));
     });
 }
 
@@ -1,11 +1,35 @@
 import { SupabaseClient } from '@supabase/supabase-js';
-import { spawn } from 'child_process';
+import { ChildProcessWithoutNullStreams } from 'child_process';
 import * as fs from 'fs';
 import * as path from 'path';
+import { spawnHidden } from '../utils';
+import { escapeConcatPath, escapeFilterValue } from '../ffmpegEscape';
 
 const TEMP_DIR = process.env.TEMP_DIR || '/tmp/dark-factory';
 const OUTPUT_DIR = process.env.OUTPUT_DIR || '/output';
 const FFMPEG_PATH = process.env.FFMPEG_PATH || 'ffmpeg';
+const FFPROBE_PATH = process.env.FFPROBE_PATH || 'ffprobe';
+// Diferença aceita entre a duração do vídeo final e a do áudio master
+const DURATION_TOLERANCE_SEC = 0.5;
+// Tempo que o FFmpeg tem para sair após SIGTERM antes do SIGKILL (docker stop espera 10s)
+const ABORT_GRACE_MS = 5000;
+// Quantidade de stderr (fora as linhas de progresso) guardada para a mensagem de erro
+const STDERR_TAIL_CHARS = 4000;
+
+// Thumbnail no padrão do YouTube: 1280x720 e no máximo 2MB
+const THUMB_WIDTH = 1280;
+const THUMB_HEIGHT = 720;
+const THUMB_MAX_BYTES = 2 * 1024 * 1024;
+const THUMB_MARGIN = 64;
+// Fonte padrão do título (vazio = fontconfig escolhe a "Sans" do sistema)
+const THUMBNAIL_FONT_FILE = process.env.THUMBNAIL_FONT_FILE || '';
+
+// Processos filhos em andamento (FFmpeg do render, ffprobe, verificação, thumbnail)
+const activeChildren = new Set<ChildProcessWithoutNullStreams>();
+// MP4 que o FFmpeg está escrevendo agora (parcial até o processo terminar)
+let activeOutputPath: string | null = null;
+// Depois do abort nenhum processo novo é iniciado
+let aborting = false;
 
 interface JobData {
     id: string;
@@ -23,25 +47,33 @@ interface JobData {
         }>;
         masterAudioUrl?: string;
     };
+    metadata?: {
+        titles?: string[];
+        thumbnailUrl?: string;
+    };
 }
 
 /**
  * Processa um job de renderização usando FFmpeg
  */
-export async function processRenderJob(supabase: SupabaseClient, job: JobData): Promise<void> {
+/**
+ * onResultSaved é chamado assim que a URL do vídeo final é gravada no job:
+ * a partir daí um shutdown não deve mais devolver o job para a fila.
+ */
+export async function processRenderJob(
+    supabase: SupabaseClient,
+    job: JobData,
+    onResultSaved?: () => void
+): Promise<void> {
     console.log(`🎬 Iniciando renderização para: ${job.theme}`);
 
     const jobDir = path.join(TEMP_DIR, job.id);
     const outputPath = path.join(OUTPUT_DIR, `${job.id}_final.mp4`);
 
     // Criar diretório de trabalho
-    if (!fs.existsSync(jobDir)) {
-        fs.mkdirSync(jobDir, { recursive: true });
-    }
-
-    if (!fs.existsSync(OUTPUT_DIR)) {
-        fs.mkdirSync(OUTPUT_DIR, { recursive: true });
-    }
+    // (I/O assíncrono para não travar o event loop / canal real-time durante arquivos grandes)
+    await fs.promises.mkdir(jobDir, { recursive: true });
+    await fs.promises.mkdir(OUTPUT_DIR, { recursive: true });
 
     // Atualizar progresso
     await updateProgress(supabase, job.id, 60, 'Baixando assets...');
@@ -54,21 +86,40 @@ export async function processRenderJob(supabase: SupabaseClient, job: JobData):
     // 2. Gerar o vídeo com FFmpeg
     await runFFmpeg(assets, outputPath, job);
 
+    // 2.1 Verificar o arquivo antes de considerar sucesso (exit code 0 não garante MP4 íntegro)
+    await verifyOutput(outputPath, assets.audio);
+
     await updateProgress(supabase, job.id, 75, 'Fazendo upload do resultado...');
 
     // 3. Upload do vídeo final para o Storage
     const finalVideoUrl = await uploadResult(supabase, job.id, outputPath);
 
-    // 4. Atualizar o job com a URL do vídeo
+    // 3.1 Thumbnail a partir da primeira cena. Falhar aqui não invalida o vídeo.
+    let thumbnailUrl: string | undefined;
+    if (assets.images.length > 0) {
+        try {
+            const thumbPath = path.join(jobDir, 'thumbnail.jpg');
+            const title = job.metadata?.titles?.[0] || job.theme;
+            const thumb = await renderThumbnail(assets.images[0], { title, darken: true }, thumbPath);
+            thumbnailUrl = await uploadFile(supabase, `videos/${job.id}/thumbnail.jpg`, thumbPath, 'image/jpeg');
+            console.log(`  🖼️ Thumbnail ${thumb.width}x${thumb.height} (${(thumb.bytes / 1024).toFixed(0)}KB)`);
+        } catch (error) {
+            console.warn(`  ⚠️ Falha ao gerar thumbnail: ${error}`);
+        }
+    }
+
+    // 4. Atualizar o job com a URL do vídeo (e da thumbnail, que o DistributionPanel exibe)
     await supabase.from('jobs').update({
         result: {
             ...job.result,
             finalVideoUrl
-        }
+        },
+        ...(thumbnailUrl && { metadata: { ...job.metadata, thumbnailUrl } })
     }).eq('id', job.id);
+    onResultSaved?.();
 
     // Limpar arquivos temporários
-    fs.rmSync(jobDir, { recursive: true, force: true });
+    await fs.promises.rm(jobDir, { recursive: true, force: true });
 
     console.log(`✅ Vídeo renderizado: ${finalVideoUrl}`);
 }
@@ -92,7 +143,7 @@ async function downloadAssets(
 
         if (data && !error) {
             const buffer = Buffer.from(await data.arrayBuffer());
-            fs.writeFileSync(audioPath, buffer);
+            await fs.promises.writeFile(audioPath, buffer);
             console.log(`  📥 Áudio baixado: ${audioFileName}`);
         }
     }
@@ -110,7 +161,7 @@ async function downloadAssets(
 
                 if (data && !error) {
                     const buffer = Buffer.from(await data.arrayBuffer());
-                    fs.writeFileSync(imagePath, buffer);
+                    await fs.promises.writeFile(imagePath, buffer);
                     images.push(imagePath);
                     console.log(`  📥 Imagem baixada: ${imageFileName}`);
                 }
@@ -126,86 +177,463 @@ async function runFFmpeg(
     outputPath: string,
     job: JobData
 ): Promise<void> {
-    return new Promise((resolve, reject) => {
-        // Construir comando FFmpeg básico
-        // Este é um exemplo simplificado - na prática, seria mais complexo
-        const args: string[] = [];
+    // Construir comando FFmpeg básico
+    // Este é um exemplo simplificado - na prática, seria mais complexo
+    const args: string[] = [];
 
-        // Input de áudio
-        if (assets.audio) {
-            args.push('-i', assets.audio);
-        }
+    // Input de áudio
+    if (assets.audio) {
+        args.push('-i', assets.audio);
+    }
 
-        // Criar um slideshow das imagens (se houver)
-        if (assets.images.length > 0) {
-            // Criar arquivo de lista para concat
-            const listPath = path.join(path.dirname(outputPath), 'images.txt');
-            const duration = job.result?.storyboard?.[0]?.duration || 5;
+    // Criar um slideshow das imagens (se houver)
+    if (assets.images.length > 0) {
+        // Criar arquivo de lista para concat
+        const listPath = path.join(path.dirname(outputPath), 'images.txt');
+        const duration = job.result?.storyboard?.[0]?.duration || 5;
 
-            const listContent = assets.images
-                .map(img => `file '${img}'\nduration ${duration}`)
-                .join('\n');
+        const listContent = assets.images
+            .map(img => `file ${escapeConcatPath(img)}\nduration ${duration}`)
+            .join('\n');
 
-            fs.writeFileSync(listPath, listContent);
+        await fs.promises.writeFile(listPath, listContent);
 
-            args.push('-f', 'concat', '-safe', '0', '-i', listPath);
-        }
+        args.push('-f', 'concat', '-safe', '0', '-i', listPath);
+    }
+
+    // Configurações de saída
+    args.push(
+        '-c:v', 'libx264',
+        '-preset', 'fast',
+        '-crf', '23',
+        '-c:a', 'aac',
+        '-b:a', '192k',
+        '-pix_fmt', 'yuv420p',
+        '-movflags', '+faststart', // moov no início: o upload/player começa sem baixar o arquivo todo
+        '-y', // Sobrescrever
+        outputPath
+    );
+
+    return new Promise((resolve, reject) => {
+        const commandLine = [FFMPEG_PATH, ...args].map(quoteArg).join(' ');
+        console.log(`  🔧 Executando: ${commandLine}`);
 
-        // Configurações de saída
-        args.push(
-            '-c:v', 'libx264',
-            '-preset', 'fast',
-            '-crf', '23',
-            '-c:a', 'aac',
-            '-b:a', '192k',
-            '-pix_fmt', 'yuv420p',
-            '-y', // Sobrescrever
-            outputPath
-        );
+        const startedAt = Date.now();
+        let stderrTail = '';
+        let pendingLine = '';
 
-        console.log(`  🔧 Executando: ${FFMPEG_PATH} ${args.join(' ')}`);
+        // Linhas de progresso vão para o console; o resto fica no tail para a mensagem de erro
+        const handleStderrLine = (line: string) => {
+            if (!line.trim()) return;
+            if (line.includes('frame=') || line.includes('time=')) {
+                process.stdout.write(`  ⏳ ${line.trim()}\r`);
+            } else {
+                stderrTail = (stderrTail + line + '\n').slice(-STDERR_TAIL_CHARS);
+            }
+        };
 
-        const ffmpeg = spawn(FFMPEG_PATH, args);
+        const ffmpeg = spawnTracked(FFMPEG_PATH, args);
+        activeOutputPath = outputPath;
 
         ffmpeg.stdout.on('data', (data) => {
             console.log(`  [FFmpeg] ${data}`);
         });
 
         ffmpeg.stderr.on('data', (data) => {
-            // FFmpeg envia progresso para stderr
-            const line = data.toString();
-            if (line.includes('frame=') || line.includes('time=')) {
-                process.stdout.write(`  ⏳ ${line.trim()}\r`);
-            }
+            // FFmpeg envia progresso para stderr (separado por \r). Um mesmo chunk pode
+            // misturar progresso e as linhas de erro finais, então tratamos linha a linha;
+            // a última parte pode estar incompleta e fica para o próximo chunk.
+            const lines = (pendingLine + data.toString()).split(/\r|\n/);
+            pendingLine = lines.pop() ?? '';
+            lines.forEach(handleStderrLine);
         });
 
-        ffmpeg.on('close', (code) => {
+        ffmpeg.on('close', (code, signal) => {
+            activeOutputPath = null;
+            handleStderrLine(pendingLine);
+            const elapsed = ((Date.now() - startedAt) / 1000).toFixed(1);
+
             if (code === 0) {
-                console.log(`\n  ✅ FFmpeg finalizado com sucesso!`);
+                console.log(`\n  ✅ FFmpeg finalizado com sucesso! (${elapsed}s)`);
                 resolve();
-            } else {
-                reject(new Error(`FFmpeg exited with code ${code}`));
+                return;
             }
+
+            // Quando o processo morre por sinal o code vem null. Dentro do container,
+            // SIGKILL quase sempre é o OOM killer do kernel.
+            const reason = signal ? `encerrado pelo sinal ${signal}` : `saiu com código ${code}`;
+            const likelyOom = signal === 'SIGKILL';
+
+            reject(new Error([
+                `FFmpeg ${reason} após ${elapsed}s` + (likelyOom ? ' (provável falta de memória / OOM)' : ''),
+                `Comando: ${commandLine}`,
+                stderrTail.trim()
+            ].filter(Boolean).join('\n')));
         });
 
         ffmpeg.on('error', (err) => {
+            activeOutputPath = null;
             reject(err);
         });
     });
 }
 
+/**
+ * Coloca entre aspas simples (estilo shell) os argumentos com espaços ou aspas,
+ * para o comando registrado no log poder ser copiado e reproduzido.
+ */
+function quoteArg(arg: string): string {
+    if (arg !== '' && !/[\s'"]/.test(arg)) return arg;
+    return `'${arg.replace(/'/g, `'\\''`)}'`;
+}
+
+/**
+ * spawnHidden + registro em activeChildren, para o abort alcançar todo processo do job.
+ * Depois do abort lança erro em vez de iniciar o processo.
+ */
+function spawnTracked(command: string, args: string[]): ChildProcessWithoutNullStreams {
+    if (aborting) {
+        throw new Error(`Worker encerrando, processo não iniciado: ${command}`);
+    }
+
+    const child = spawnHidden(command, args);
+    activeChildren.add(child);
+    child.once('close', () => activeChildren.delete(child));
+    child.once('error', () => activeChildren.delete(child));
+    return child;
+}
+
+/**
+ * Interrompe todos os processos do job em andamento (FFmpeg, ffprobe...) e, se o
+ * FFmpeg ainda estava escrevendo o vídeo, apaga o arquivo parcial para não deixar
+ * um MP4 corrompido em OUTPUT_DIR.
+ */
+export async function abortActiveRender(): Promise<void> {
+    aborting = true;
+    const partialOutput = activeOutputPath;
+
+    await Promise.all([...activeChildren].map(child => new Promise<void>((resolve) => {
+        const forceKill = setTimeout(() => child.kill('SIGKILL'), ABORT_GRACE_MS);
+        child.once('close', () => {
+            clearTimeout(forceKill);
+            resolve();
+        });
+        child.kill('SIGTERM');
+    })));
+
+    if (partialOutput) {
+        await fs.promises.rm(partialOutput, { force: true });
+        console.log(`  🧹 Renderização abortada, arquivo parcial removido: ${partialOutput}`);
+    }
+}
+
+/**
+ * Último recurso quando o processo está saindo sem passar pelo shutdown
+ * (ex: exceção não tratada): não deixa FFmpeg/ffprobe órfãos.
+ */
+export function killActiveRenderSync(): void {
+    for (const child of activeChildren) {
+        child.kill('SIGKILL');
+    }
+}
+
+interface ProbeResult {
+    format?: { duration?: string };
+    streams?: Array<{ codec_type?: string; duration?: string; bit_rate?: string; width?: number; height?: number }>;
+}
+
+/**
+ * Verifica o vídeo renderizado. O FFmpeg pode sair com code 0 e mesmo assim
+ * deixar um arquivo truncado (disco cheio durante o mux) ou com áudio vazio.
+ * Lança erro, o que marca o job como FAILED em index.ts.
+ */
+async function verifyOutput(outputPath: string, masterAudioPath: string): Promise<void> {
+    const fail = (reason: string) => new Error(`Verificação do vídeo final falhou: ${reason}`);
+
+    const output = await probeMedia(outputPath);
+    const streams = output.streams || [];
+    const video = streams.find(s => s.codec_type === 'video');
+    const audio = streams.find(s => s.codec_type === 'audio');
+    const duration = parseFloat(output.format?.duration || '0');
+
+    if (!video) throw fail('nenhum stream de vídeo');
+    if (video.bit_rate === '0') throw fail('stream de vídeo vazio');
+    if (!(duration > 0)) throw fail('duração zero');
+
+    if (masterAudioPath) {
+        if (!audio) throw fail('nenhum stream de áudio');
+        const audioDuration = parseFloat(audio.duration || '0');
+        if (!(audioDuration > 0) || audio.bit_rate === '0') {
+            throw fail('stream de áudio vazio');
+        }
+
+        // Compara o stream de áudio, não o container: o slideshow não usa -shortest e pode
+        // durar mais que a narração. Áudio mais curto que o master = arquivo truncado.
+        const expected = parseFloat((await probeMedia(masterAudioPath)).format?.duration || '0');
+        if (expected - audioDuration > DURATION_TOLERANCE_SEC) {
+            throw fail(`áudio com ${audioDuration.toFixed(2)}s, narração tem ${expected.toFixed(2)}s: arquivo provavelmente truncado`);
+        }
+    }
+
+    // Decodificar o último segundo pega truncamentos que o header não denuncia
+    const tail = await runProcess(FFMPEG_PATH, ['-v', 'error', '-sseof', '-1', '-i', outputPath, '-f', 'null', '-']);
+    if (tail.code !== 0 || tail.stderr.trim()) {
+        throw fail(`erro ao decodificar o final do arquivo: ${tail.stderr.trim() || `code ${tail.code}`}`);
+    }
+
+    if (!(await isFaststart(outputPath))) {
+        throw fail('moov atom não está no início do arquivo (faststart)');
+    }
+
+    console.log(`  🔍 Vídeo verificado: ${duration.toFixed(2)}s`);
+}
+
+/**
+ * Percorre os atoms de topo do MP4 e diz se o 'moov' vem antes do 'mdat'.
+ * Só lê os headers (8-16 bytes por atom), não o conteúdo.
+ */
+async function isFaststart(filePath: string): Promise<boolean> {
+    const file = await fs.promises.open(filePath, 'r');
+
+    try {
+        const { size } = await file.stat();
+        const header = Buffer.alloc(16);
+        let offset = 0;
+
+        while (offset + 8 <= size) {
+            // Perto do fim do arquivo o read devolve menos de 16 bytes: o resto do buffer é lixo do atom anterior
+            const { bytesRead } = await file.read(header, 0, 16, offset);
+            if (bytesRead < 8) return false;
+
+            const type = header.toString('latin1', 4, 8);
+
+            if (type === 'moov') return true;
+            if (type === 'mdat') return false;
+
+            let atomSize = header.readUInt32BE(0);
+            if (atomSize === 1) {
+                if (bytesRead < 16) return false;
+                atomSize = Number(header.readBigUInt64BE(8)); // tamanho estendido (64 bits)
+            }
+            if (atomSize < 8) return false; // 0 = "até o fim do arquivo", ou atom corrompido
+
+            offset += atomSize;
+        }
+
+        return false;
+    } finally {
+        await file.close();
+    }
+}
+
+async function probeMedia(filePath: string): Promise<ProbeResult> {
+    const { code, stdout, stderr } = await runProcess(FFPROBE_PATH, [
+        '-v', 'error',
+        '-print_format', 'json',
+        '-show_format',
+        '-show_streams',
+        filePath
+    ]);
+
+    if (code !== 0) {
+        throw new Error(`ffprobe falhou para ${filePath}: ${stderr.trim() || `code ${code}`}`);
+    }
+
+    return JSON.parse(stdout) as ProbeResult;
+}
+
+function runProcess(command: string, args: string[]): Promise<{ code: number | null; stdout: string; stderr: string }> {
+    return new Promise((resolve, reject) => {
+        const child = spawnTracked(command, args);
+        let stdout = '';
+        let stderr = '';
+
+        child.stdout.on('data', (data) => { stdout += data.toString(); });
+        child.stderr.on('data', (data) => { stderr += data.toString(); });
+        child.on('close', (code) => resolve({ code, stdout, stderr }));
+        child.on('error', (err) => reject(err));
+    });
+}
+
+export interface ThumbnailSpec {
+    title: string;
+    fontFile?: string;      // .ttf/.otf; padrão THUMBNAIL_FONT_FILE
+    fontColor?: string;     // cor ffmpeg (ex: 'white', '#FFD700')
+    strokeColor?: string;
+    strokeWidth?: number;
+    position?: 'TOP' | 'CENTER' | 'BOTTOM';
+    darken?: boolean;       // gradiente escuro atrás do texto
+    overlayPath?: string;   // PNG (ex: logo do canal) centralizado sobre a imagem
+}
+
+/**
+ * Gera a thumbnail 1280x720 com o título em um único frame do FFmpeg
+ * (scale/crop + gradiente + overlay + drawtext), reduzindo a qualidade do
+ * JPEG até caber em THUMB_MAX_BYTES.
+ */
+export async function renderThumbnail(
+    backgroundPath: string,
+    spec: ThumbnailSpec,
+    outputPath: string
+): Promise<{ width: number; height: number; bytes: number }> {
+    const position = spec.position || 'BOTTOM';
+    const { lines, fontSize } = fitTitle(spec.title);
+
+    // textfile evita escapar o título (aspas, '%', ':' ...) dentro do filtergraph
+    const textPath = `${outputPath}.txt`;
+    await fs.promises.writeFile(textPath, lines.join('\n'), 'utf-8');
+
+    const filters = [`[0:v]scale=${THUMB_WIDTH}:${THUMB_HEIGHT}:force_original_aspect_ratio=increase,crop=${THUMB_WIDTH}:${THUMB_HEIGHT},setsar=1`];
+
+    if (spec.darken) {
+        filters.push(...gradientBoxes(position));
+    }
+
+    const inputs = ['-i', backgroundPath];
+    let graph = filters.join(',') + '[bg]';
+    let lastLabel = 'bg';
+
+    if (spec.overlayPath) {
+        inputs.push('-i', spec.overlayPath);
+        graph += `;[1:v]scale=${THUMB_WIDTH}:${THUMB_HEIGHT}:force_original_aspect_ratio=decrease[ov]`;
+        graph += `;[bg][ov]overlay=(W-w)/2:(H-h)/2[withov]`;
+        lastLabel = 'withov';
+    }
+
+    const fontFile = spec.fontFile || THUMBNAIL_FONT_FILE;
+    const y = position === 'TOP' ? `${THUMB_MARGIN}` : position === 'CENTER' ? '(h-text_h)/2' : `h-text_h-${THUMB_MARGIN}`;
+    const drawtext = [
+        fontFile ? `fontfile=${escapeFilterValue(fontFile)}` : 'font=Sans',
+        `textfile=${escapeFilterValue(textPath)}`,
+        'expansion=none',
+        `fontsize=${fontSize}`,
+        `fontcolor=${escapeFilterValue(spec.fontColor || 'white')}`,
+        `borderw=${spec.strokeWidth ?? 6}`,
+        `bordercolor=${escapeFilterValue(spec.strokeColor || 'black')}`,
+        'line_spacing=12',
+        'x=(w-text_w)/2',
+        `y=${y}`
+    ].join(':');
+    graph += `;[${lastLabel}]drawtext=${drawtext}[thumb]`;
+
+    try {
+        // -q:v do MJPEG: 2 = melhor qualidade, 31 = pior
+        for (let quality = 2; quality <= 31; quality += 3) {
+            const { code, stderr } = await runProcess(FFMPEG_PATH, [
+                ...inputs,
+                '-filter_complex', graph,
+                '-map', '[thumb]',
+                '-frames:v', '1',
+                '-q:v', `${quality}`,
+                '-y', outputPath
+            ]);
+
+            if (code !== 0) {
+                throw new Error(`FFmpeg saiu com código ${code} ao gerar a thumbnail: ${stderr.trim()}`);
+            }
+
+            const { size } = await fs.promises.stat(outputPath);
+            if (size <= THUMB_MAX_BYTES) {
+                const probe = await probeMedia(outputPath);
+                const video = probe.streams?.find(s => s.codec_type === 'video');
+                return { width: video?.width || THUMB_WIDTH, height: video?.height || THUMB_HEIGHT, bytes: size };
+            }
+        }
+
+        throw new Error(`thumbnail não coube em ${THUMB_MAX_BYTES} bytes nem na menor qualidade`);
+    } finally {
+        await fs.promises.rm(textPath, { force: true });
+    }
+}
+
+// Largura média de um caractere em relação ao fontsize (fontes sans bold)
+const AVG_CHAR_WIDTH = 0.58;
+const TITLE_MAX_LINES = 3;
+const TITLE_MAX_FONT = 110;
+const TITLE_MIN_FONT = 48;
+
+/**
+ * Escolhe o maior fontsize em que o título cabe em até TITLE_MAX_LINES linhas.
+ * O drawtext não quebra linha nem reduz a fonte sozinho e simplesmente corta o texto fora do frame.
+ */
+function fitTitle(title: string): { lines: string[]; fontSize: number } {
+    const maxWidth = THUMB_WIDTH - 2 * THUMB_MARGIN;
+    const words = title.trim().split(/\s+/).filter(Boolean);
+
+    for (let fontSize = TITLE_MAX_FONT; fontSize >= TITLE_MIN_FONT; fontSize -= 6) {
+        const maxChars = Math.floor(maxWidth / (fontSize * AVG_CHAR_WIDTH));
+        const lines = wrapWords(words, maxChars);
+        if (lines.length <= TITLE_MAX_LINES && lines.every(l => l.length <= maxChars)) {
+            return { lines, fontSize };
+        }
+    }
+
+    // Nem no menor tamanho coube: corta palavras longas e termina com reticências
+    const maxChars = Math.floor(maxWidth / (TITLE_MIN_FONT * AVG_CHAR_WIDTH));
+    const lines = wrapWords(words, maxChars).map(l => l.length > maxChars ? `${l.slice(0, maxChars - 1)}…` : l);
+    if (lines.length > TITLE_MAX_LINES) {
+        lines.length = TITLE_MAX_LINES;
+        lines[TITLE_MAX_LINES - 1] = `${lines[TITLE_MAX_LINES - 1].slice(0, maxChars - 1)}…`;
+    }
+    return { lines, fontSize: TITLE_MIN_FONT };
+}
+
+function wrapWords(words: string[], maxChars: number): string[] {
+    const lines: string[] = [];
+    let current = '';
+
+    for (const word of words) {
+        if (current && `${current} ${word}`.length > maxChars) {
+            lines.push(current);
+            current = word;
+        } else {
+            current = current ? `${current} ${word}` : word;
+        }
+    }
+    if (current) lines.push(current);
+
+    return lines;
+}
+
+/**
+ * Aproxima um gradiente com faixas de drawbox de opacidade crescente
+ * em direção à borda onde fica o texto (ou escurece tudo se o texto é centralizado).
+ */
+function gradientBoxes(position: 'TOP' | 'CENTER' | 'BOTTOM'): string[] {
+    if (position === 'CENTER') {
+        return ['drawbox=x=0:y=0:w=iw:h=ih:color=black@0.35:t=fill'];
+    }
+
+    const steps = 6;
+    const bandHeight = THUMB_HEIGHT / 2 / steps;
+
+    return Array.from({ length: steps }, (_, i) => {
+        const alpha = (0.7 * (i + 1) / steps).toFixed(2);
+        const y = position === 'BOTTOM' ? THUMB_HEIGHT / 2 + i * bandHeight : THUMB_HEIGHT / 2 - (i + 1) * bandHeight;
+        return `drawbox=x=0:y=${y}:w=iw:h=${bandHeight}:color=black@${alpha}:t=fill`;
+    });
+}
+
 async function uploadResult(
     supabase: SupabaseClient,
     jobId: string,
     filePath: string
 ): Promise<string> {
-    const fileBuffer = fs.readFileSync(filePath);
-    const fileName = `videos/${jobId}/final.mp4`;
+    return uploadFile(supabase, `videos/${jobId}/final.mp4`, filePath, 'video/mp4');
+}
+
+async function uploadFile(
+    supabase: SupabaseClient,
+    fileName: string,
+    filePath: string,
+    contentType: string
+): Promise<string> {
+    const fileBuffer = await fs.promises.readFile(filePath);
 
     const { error } = await supabase.storage
         .from('assets')
         .upload(fileName, fileBuffer, {
-            contentType: 'video/mp4',
+            contentType,
             upsert: true
         });
 
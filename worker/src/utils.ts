@@ -1,6 +1,8 @@
 
 import fs from 'fs';
-import ffmpeg from 'fluent-ffmpeg';
+import { spawn, ChildProcessWithoutNullStreams, SpawnOptionsWithoutStdio } from 'child_process';
+
+const FFPROBE_PATH = process.env.FFPROBE_PATH || 'ffprobe';
 
 /**
  * Converte PCM Base64 para buffer WAV com header.
@@ -20,13 +22,37 @@ export const saveBase64ToFile = async (base64Data: string, filePath: string): Pr
 
 export const getAudioDuration = (filePath: string): Promise<number> => {
     return new Promise((resolve, reject) => {
-        ffmpeg.ffprobe(filePath, (err: Error | null, metadata: { format: { duration?: number } }) => {
-            if (err) return reject(err);
-            resolve(metadata.format.duration || 0);
+        const probe = spawnHidden(FFPROBE_PATH, [
+            '-v', 'error',
+            '-show_entries', 'format=duration',
+            '-of', 'default=noprint_wrappers=1:nokey=1',
+            filePath
+        ]);
+        let stdout = '';
+        let stderr = '';
+
+        probe.stdout.on('data', (data) => { stdout += data.toString(); });
+        probe.stderr.on('data', (data) => { stderr += data.toString(); });
+        probe.on('close', (code) => {
+            if (code !== 0) return reject(new Error(`ffprobe falhou para ${filePath}: ${stderr.trim() || `code ${code}`}`));
+            resolve(parseFloat(stdout) || 0);
         });
+        probe.on('error', (err) => reject(err));
     });
 };
 
+/**
+ * Inicia um processo externo (ffmpeg, ffprobe, ...) sem abrir janela de console.
+ * No Windows cada spawn sem windowsHide pisca uma janela preta; use sempre este helper.
+ */
+export const spawnHidden = (
+    command: string,
+    args: string[],
+    options: SpawnOptionsWithoutStdio = {}
+): ChildProcessWithoutNullStreams => {
+    return spawn(command, args, { ...options, windowsHide: true });
+};
+
 function createWavHeader(dataLength: number, sampleRate: number): Buffer {
     const numChannels = 1;
     const bitsPerSample = 16;